@@ -0,0 +1,133 @@
+#ifndef KVS_H
+#define KVS_H
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define KVS_OK 0
+
+/**
+ * The key was not found.
+ */
+#define KVS_ERR_KEY_NOT_FOUND 1
+
+/**
+ * An I/O error occurred.
+ */
+#define KVS_ERR_IO 2
+
+/**
+ * A serialization or deserialization error occurred.
+ */
+#define KVS_ERR_SERDE 3
+
+/**
+ * An unexpected command type was found in the log.
+ */
+#define KVS_ERR_UNEXPECTED_COMMAND_TYPE 4
+
+/**
+ * An unexpected engine type was found.
+ */
+#define KVS_ERR_UNEXPECTED_ENGINE_TYPE 5
+
+/**
+ * A required pointer argument was NULL.
+ */
+#define KVS_ERR_NULL_POINTER 6
+
+/**
+ * A string was not valid UTF-8 or contained a NUL byte.
+ */
+#define KVS_ERR_INVALID_STRING 7
+
+/**
+ * A dump blob was truncated, corrupted or of an unsupported version.
+ */
+#define KVS_ERR_INVALID_DUMP 8
+
+/**
+ * The call panicked. The store may be left inconsistent and should be closed.
+ */
+#define KVS_ERR_PANIC 9
+
+/**
+ * An opaque handle to an open `KvStore`.
+ *
+ * It is not thread-safe, calls on the same handle must not run concurrently.
+ */
+typedef struct KvsHandle KvsHandle;
+
+/**
+ * Opens a store at the given directory path and writes its handle to `handle`.
+ *
+ * The handle must be released with `kvs_close`.
+ *
+ * # Safety
+ *
+ * `path` must be a valid NUL-terminated string and `handle` a valid pointer
+ * to write the handle to. The returned handle must not be used by several
+ * threads at the same time.
+ */
+int kvs_open(const char *path, KvsHandle **handle);
+
+/**
+ * Sets the value of a string key to a string.
+ *
+ * # Safety
+ *
+ * `handle` must come from `kvs_open` and not be closed yet. `key` and `value`
+ * must be valid NUL-terminated strings.
+ */
+int kvs_set(KvsHandle *handle, const char *key, const char *value);
+
+/**
+ * Gets the string value of a given string key and writes it to `value`.
+ *
+ * Returns `KVS_ERR_KEY_NOT_FOUND` and writes NULL if the key does not exist.
+ * The returned string must be released with `kvs_free_string`.
+ *
+ * # Safety
+ *
+ * `handle` must come from `kvs_open` and not be closed yet. `key` must be a
+ * valid NUL-terminated string and `value` a valid pointer to write the value to.
+ */
+int kvs_get(KvsHandle *handle, const char *key, char **value);
+
+/**
+ * Removes a given key.
+ *
+ * # Safety
+ *
+ * `handle` must come from `kvs_open` and not be closed yet. `key` must be a
+ * valid NUL-terminated string.
+ */
+int kvs_remove(KvsHandle *handle, const char *key);
+
+/**
+ * Closes a store opened by `kvs_open`. Passing NULL is a no-op.
+ *
+ * # Safety
+ *
+ * `handle` must come from `kvs_open` and must not be used after this call.
+ */
+void kvs_close(KvsHandle *handle);
+
+/**
+ * Releases a string returned by `kvs_get`. Passing NULL is a no-op.
+ *
+ * # Safety
+ *
+ * `s` must come from `kvs_get` and must not be used after this call.
+ */
+void kvs_free_string(char *s);
+
+#endif /* KVS_H */
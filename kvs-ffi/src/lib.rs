@@ -0,0 +1,21 @@
+// MIT License
+//
+// Copyright (c) 2023 Chunfung
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+#![deny(missing_docs)]
+//! The C library of the kvs key-value store.
+//!
+//! It links the C ABI of `kvs::ffi` into a shared and a static library, declared
+//! by `include/kvs.h` of the `kvs` crate.
+
+pub use kvs::ffi::*;
@@ -13,15 +13,17 @@
 // copies or substantial portions of the Software.
 
 use clap::{Parser, ValueEnum};
-use kvs::{KvsError, Result};
+use kvs::{KvStore, KvsError, OpenProgress, Result};
 use std::env::current_dir;
 use std::fs;
 use std::net::SocketAddr;
 use std::process::exit;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 
 const DEFAULT_LISTENING_ADDRESS: &str = "127.0.0.1:4000";
 const DEFAULT_ENGINE: Engine = Engine::kvs;
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Parser)]
 #[command(name = "kvs-server", version)]
@@ -91,7 +93,33 @@ fn run(cli: Cli) -> Result<()> {
     fs::write(current_dir()?.join("engine"), format!("{:?}", engine))?;
 
     match engine {
-        Engine::kvs => Ok(()),
+        Engine::kvs => {
+            KvStore::open_with_progress(current_dir()?, replay_progress())?;
+            Ok(())
+        }
         Engine::sled => Ok(()),
     }
 }
+
+/// Returns a callback printing the progress of the log replay.
+///
+/// A line is printed after every replayed log, and at most every `PROGRESS_INTERVAL`
+/// while a log is being replayed.
+fn replay_progress() -> impl FnMut(&OpenProgress) {
+    let mut logs_replayed = 0;
+    let mut printed_at = Instant::now();
+    move |progress| {
+        if progress.logs_replayed == logs_replayed && printed_at.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        logs_replayed = progress.logs_replayed;
+        printed_at = Instant::now();
+        eprintln!(
+            "Replaying logs: {}/{} logs, {} bytes, {} records",
+            progress.logs_replayed,
+            progress.logs_discovered,
+            progress.bytes_replayed,
+            progress.records_loaded
+        );
+    }
+}
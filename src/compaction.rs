@@ -0,0 +1,75 @@
+// MIT License
+//
+// Copyright (c) 2023 Chunfung
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+use crate::error::KvsError;
+use std::time::Duration;
+
+/// Observes the progress of compactions run by a `KvStore`.
+///
+/// An observer is registered with `KvStoreBuilder::compaction_observer`.
+/// All methods have empty default implementations, so an observer only
+/// overrides the events it cares about.
+///
+/// ```rust
+/// # use kvs::{CompactionObserver, CompactionReport, KvStore, Result};
+/// # fn try_main() -> Result<()> {
+/// use std::env::current_dir;
+///
+/// struct Logger;
+///
+/// impl CompactionObserver for Logger {
+///     fn finished(&self, report: &CompactionReport) {
+///         eprintln!("compacted {} records in {:?}", report.records, report.elapsed);
+///     }
+/// }
+///
+/// let mut store = KvStore::builder()
+///     .compaction_observer(Logger)
+///     .open(current_dir()?)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait CompactionObserver: Send {
+    /// Called before a compaction starts.
+    fn started(&self) {}
+
+    /// Called after each record is copied into the compacted log, with the
+    /// total number of bytes and records copied so far.
+    fn progressed(&self, _bytes: u64, _records: u64) {}
+
+    /// Called after a compaction completes successfully.
+    fn finished(&self, _report: &CompactionReport) {}
+
+    /// Called when a compaction fails, before the error is returned.
+    fn failed(&self, _err: &KvsError) {}
+}
+
+/// Summary of a completed compaction.
+#[derive(Debug, Clone)]
+pub struct CompactionReport {
+    /// Number of live records copied into the compacted log.
+    pub records: u64,
+    /// Number of tombstones kept because their grace period has not elapsed.
+    pub tombstones_retained: u64,
+    /// Number of tombstones purged because their grace period has elapsed.
+    pub tombstones_purged: u64,
+    /// Number of bytes written to the compacted log.
+    pub bytes_written: u64,
+    /// Number of stale bytes that were due for compaction.
+    pub stale_bytes: u64,
+    /// Number of stale log files removed.
+    pub logs_removed: u64,
+    /// Time taken by the compaction.
+    pub elapsed: Duration,
+}
@@ -0,0 +1,74 @@
+// MIT License
+//
+// Copyright (c) 2023 Chunfung
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+//! The blob format of `KvStore::dump` and `KvStore::restore`.
+//!
+//! A blob is a version byte, a json-serialized `Dump`, and a little-endian
+//! CRC-32 of both.
+
+use super::Result;
+use crate::error::KvsError;
+use serde::{Deserialize, Serialize};
+
+const DUMP_VERSION: u8 = 1;
+const CHECKSUM_LEN: usize = 4;
+
+/// The content of a dumped key.
+///
+/// New metadata is added as `#[serde(default)]` fields, so older blobs stay readable.
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct Dump {
+    pub(crate) value: String,
+}
+
+/// Serializes a dump into a versioned, checksummed blob.
+pub(crate) fn encode(dump: &Dump) -> Result<Vec<u8>> {
+    let mut blob = vec![DUMP_VERSION];
+    serde_json::to_writer(&mut blob, dump)?;
+    let checksum = crc32(&blob);
+    blob.extend_from_slice(&checksum.to_le_bytes());
+    Ok(blob)
+}
+
+/// Deserializes a dump from a blob produced by `encode`.
+///
+/// # Errors
+///
+/// It returns `KvsError::InvalidDump` if the blob is truncated, corrupted or
+/// of an unsupported version.
+pub(crate) fn decode(blob: &[u8]) -> Result<Dump> {
+    if blob.len() < 1 + CHECKSUM_LEN {
+        return Err(KvsError::InvalidDump);
+    }
+    let (content, checksum) = blob.split_at(blob.len() - CHECKSUM_LEN);
+    let mut expected = [0; CHECKSUM_LEN];
+    expected.copy_from_slice(checksum);
+    if crc32(content) != u32::from_le_bytes(expected) || content[0] != DUMP_VERSION {
+        return Err(KvsError::InvalidDump);
+    }
+    serde_json::from_slice(&content[1..]).map_err(|_| KvsError::InvalidDump)
+}
+
+/// Computes the CRC-32 (IEEE 802.3) checksum of the given bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -42,6 +42,11 @@ pub enum KvsError {
     /// It indicated a corrupted log or a program bug.
     #[fail(display = "Unexpected engine type")]
     UnexpectedEngineType,
+
+    /// Invalid dump error.
+    /// It indicated a truncated or corrupted blob, or one of an unsupported version.
+    #[fail(display = "Invalid dump")]
+    InvalidDump,
 }
 
 impl From<io::Error> for KvsError {
@@ -0,0 +1,240 @@
+// MIT License
+//
+// Copyright (c) 2023 Chunfung
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+//! C bindings for embedding a `KvStore` in non-Rust programs.
+//!
+//! Every function returns `KVS_OK` on success or one of the `KVS_ERR_*` codes.
+//! Strings are NUL-terminated UTF-8. Values returned by `kvs_get` are owned by
+//! the caller and must be released with `kvs_free_string`. Panics are caught
+//! and reported as `KVS_ERR_PANIC` instead of unwinding into the caller.
+//!
+//! A `KvsHandle` is not thread-safe: every call borrows the store mutably, so
+//! calls on the same handle must not run concurrently. Callers sharing a handle
+//! between threads must serialize the calls, e.g. with a mutex.
+//!
+//! The C header lives in `include/kvs.h` and is generated with
+//! `cbindgen --config cbindgen.toml --output include/kvs.h`. The `kvs-ffi`
+//! package links this module into a shared and a static C library.
+
+use crate::{KvStore, KvsError, Result};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// The call succeeded.
+pub const KVS_OK: c_int = 0;
+/// The key was not found.
+pub const KVS_ERR_KEY_NOT_FOUND: c_int = 1;
+/// An I/O error occurred.
+pub const KVS_ERR_IO: c_int = 2;
+/// A serialization or deserialization error occurred.
+pub const KVS_ERR_SERDE: c_int = 3;
+/// An unexpected command type was found in the log.
+pub const KVS_ERR_UNEXPECTED_COMMAND_TYPE: c_int = 4;
+/// An unexpected engine type was found.
+pub const KVS_ERR_UNEXPECTED_ENGINE_TYPE: c_int = 5;
+/// A required pointer argument was NULL.
+pub const KVS_ERR_NULL_POINTER: c_int = 6;
+/// A string was not valid UTF-8 or contained a NUL byte.
+pub const KVS_ERR_INVALID_STRING: c_int = 7;
+/// A dump blob was truncated, corrupted or of an unsupported version.
+pub const KVS_ERR_INVALID_DUMP: c_int = 8;
+/// The call panicked. The store may be left inconsistent and should be closed.
+pub const KVS_ERR_PANIC: c_int = 9;
+
+/// An opaque handle to an open `KvStore`.
+///
+/// It is not thread-safe, calls on the same handle must not run concurrently.
+pub struct KvsHandle {
+    store: KvStore,
+}
+
+/// Opens a store at the given directory path and writes its handle to `handle`.
+///
+/// The handle must be released with `kvs_close`.
+///
+/// # Safety
+///
+/// `path` must be a valid NUL-terminated string and `handle` a valid pointer
+/// to write the handle to. The returned handle must not be used by several
+/// threads at the same time.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_open(path: *const c_char, handle: *mut *mut KvsHandle) -> c_int {
+    guard(|| {
+        if handle.is_null() {
+            return KVS_ERR_NULL_POINTER;
+        }
+        *handle = ptr::null_mut();
+        let path = match to_str(path) {
+            Ok(path) => path,
+            Err(code) => return code,
+        };
+        match KvStore::open(path) {
+            Ok(store) => {
+                *handle = Box::into_raw(Box::new(KvsHandle { store }));
+                KVS_OK
+            }
+            Err(err) => error_code(&err),
+        }
+    })
+}
+
+/// Sets the value of a string key to a string.
+///
+/// # Safety
+///
+/// `handle` must come from `kvs_open` and not be closed yet. `key` and `value`
+/// must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_set(
+    handle: *mut KvsHandle,
+    key: *const c_char,
+    value: *const c_char,
+) -> c_int {
+    guard(|| {
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return KVS_ERR_NULL_POINTER,
+        };
+        let (key, value) = match (to_str(key), to_str(value)) {
+            (Ok(key), Ok(value)) => (key, value),
+            (Err(code), _) | (_, Err(code)) => return code,
+        };
+        to_code(handle.store.set(key.to_owned(), value.to_owned()))
+    })
+}
+
+/// Gets the string value of a given string key and writes it to `value`.
+///
+/// Returns `KVS_ERR_KEY_NOT_FOUND` and writes NULL if the key does not exist.
+/// The returned string must be released with `kvs_free_string`.
+///
+/// # Safety
+///
+/// `handle` must come from `kvs_open` and not be closed yet. `key` must be a
+/// valid NUL-terminated string and `value` a valid pointer to write the value to.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_get(
+    handle: *mut KvsHandle,
+    key: *const c_char,
+    value: *mut *mut c_char,
+) -> c_int {
+    guard(|| {
+        if value.is_null() {
+            return KVS_ERR_NULL_POINTER;
+        }
+        *value = ptr::null_mut();
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return KVS_ERR_NULL_POINTER,
+        };
+        let key = match to_str(key) {
+            Ok(key) => key,
+            Err(code) => return code,
+        };
+        match handle.store.get(key.to_owned()) {
+            Ok(Some(found)) => match CString::new(found) {
+                Ok(found) => {
+                    *value = found.into_raw();
+                    KVS_OK
+                }
+                Err(_) => KVS_ERR_INVALID_STRING,
+            },
+            Ok(None) => KVS_ERR_KEY_NOT_FOUND,
+            Err(err) => error_code(&err),
+        }
+    })
+}
+
+/// Removes a given key.
+///
+/// # Safety
+///
+/// `handle` must come from `kvs_open` and not be closed yet. `key` must be a
+/// valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_remove(handle: *mut KvsHandle, key: *const c_char) -> c_int {
+    guard(|| {
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return KVS_ERR_NULL_POINTER,
+        };
+        let key = match to_str(key) {
+            Ok(key) => key,
+            Err(code) => return code,
+        };
+        to_code(handle.store.remove(key.to_owned()))
+    })
+}
+
+/// Closes a store opened by `kvs_open`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `handle` must come from `kvs_open` and must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_close(handle: *mut KvsHandle) {
+    if !handle.is_null() {
+        guard(|| {
+            drop(Box::from_raw(handle));
+            KVS_OK
+        });
+    }
+}
+
+/// Releases a string returned by `kvs_get`. Passing NULL is a no-op.
+///
+/// # Safety
+///
+/// `s` must come from `kvs_get` and must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn kvs_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Runs the body of an exported function, turning a panic into `KVS_ERR_PANIC`.
+fn guard(body: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(KVS_ERR_PANIC)
+}
+
+/// Borrows a NUL-terminated C string as `&str`.
+unsafe fn to_str<'a>(s: *const c_char) -> std::result::Result<&'a str, c_int> {
+    if s.is_null() {
+        return Err(KVS_ERR_NULL_POINTER);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| KVS_ERR_INVALID_STRING)
+}
+
+fn to_code(res: Result<()>) -> c_int {
+    match res {
+        Ok(()) => KVS_OK,
+        Err(err) => error_code(&err),
+    }
+}
+
+fn error_code(err: &KvsError) -> c_int {
+    match err {
+        KvsError::Io(_) => KVS_ERR_IO,
+        KvsError::KeyNotFound => KVS_ERR_KEY_NOT_FOUND,
+        KvsError::Serde(_) => KVS_ERR_SERDE,
+        KvsError::UnexpectedCommandType => KVS_ERR_UNEXPECTED_COMMAND_TYPE,
+        KvsError::UnexpectedEngineType => KVS_ERR_UNEXPECTED_ENGINE_TYPE,
+        KvsError::InvalidDump => KVS_ERR_INVALID_DUMP,
+    }
+}
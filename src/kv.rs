@@ -13,27 +13,33 @@
 // copies or substantial portions of the Software.
 
 use super::Result;
+use crate::compaction::{CompactionObserver, CompactionReport};
+use crate::dump::{self, Dump};
 use crate::error::KvsError;
+use crate::storage::{FileStorage, Storage};
 use serde::{Deserialize, Serialize};
 use serde_json::Deserializer;
 use std::{
     borrow::BorrowMut,
-    collections::{BTreeMap, HashMap},
-    ffi::OsStr,
-    fs,
-    fs::{File, OpenOptions},
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
     io,
     io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    mem,
     ops::Range,
-    path::{Path, PathBuf},
+    path::PathBuf,
+    time::Duration,
 };
 
 const COMPACTION_THRESHOLD: u64 = 1024 * 1024;
+const HAS_SYSTEM_CLOCK: bool = !cfg!(all(target_arch = "wasm32", target_os = "unknown"));
 
 /// The `KvStore` stores string key/value pairs.
 ///
-/// Key/value pairs are persisted to disk in log files. Log files are named after
-/// monotonically increasing generation numbers with a `log` extension name.
+/// Key/value pairs are persisted in logs named after monotonically increasing
+/// generation numbers. By default the logs are files in a directory, see
+/// `FileStorage`; any other `Storage` can be used with
+/// `KvStoreBuilder::open_with_storage`.
 /// A `BTreeMap` in memory stores the keys and the value locations for fast query.
 ///
 /// ```rust
@@ -47,18 +53,29 @@ const COMPACTION_THRESHOLD: u64 = 1024 * 1024;
 /// # Ok(())
 /// # }
 /// ```
-pub struct KvStore {
-    path: PathBuf,
+pub struct KvStore<S: Storage = FileStorage> {
+    storage: S,
     log: u64,
     // the number of bytes representing "stale" commands that could be
-    // deleted during a compaction.
+    // deleted during a compaction. A remove command counts once its
+    // tombstone expires or is replaced.
     uncompacted: u64,
     // reader of the current log.
-    readers: HashMap<u64, BufReaderWithPos<File>>,
+    readers: HashMap<u64, BufReaderWithPos<S::Reader>>,
     // writer of the current log.
-    writer: BufWriterWithPos<File>,
+    writer: BufWriterWithPos<S::Writer>,
     // map log file to the record args
     records: BTreeMap<String, RecordArgs>,
+    // map removed keys to their remove commands kept in the log.
+    tombstones: HashMap<String, Tombstone>,
+    // removal times and keys of tombstones, earliest first, to find the expired ones.
+    expiring: BinaryHeap<Reverse<(u64, String)>>,
+    // how long a tombstone survives compactions after its removal.
+    grace_period: Duration,
+    // returns the time elapsed since the Unix epoch.
+    clock: Box<dyn Fn() -> Duration + Send>,
+    // notified about the progress of compactions.
+    observer: Option<Box<dyn CompactionObserver>>,
 }
 
 impl KvStore {
@@ -70,34 +87,31 @@ impl KvStore {
     ///
     /// It propagates I/O or deserialization errors during the log replay.
     pub fn open(path: impl Into<PathBuf>) -> Result<KvStore> {
-        let path = path.into();
-        fs::create_dir_all(&path)?;
-
-        let mut readers = HashMap::new();
-        let mut records = BTreeMap::new();
-        let mut uncompacted = 0;
-
-        let log_list = sorted_log_list(&path)?;
-
-        for &log in &log_list {
-            let mut reader = BufReaderWithPos::new(File::open(log_path(&path, log))?)?;
-            uncompacted += load(log, &mut reader, &mut records)?;
-            readers.insert(log, reader);
-        }
+        KvStore::builder().open(path)
+    }
 
-        let log = log_list.last().unwrap_or(&0) + 1;
-        let writer = new_log_file(&path, log, &mut readers)?;
+    /// Opens a `KvStore` with the given path, reporting the progress of the log replay.
+    ///
+    /// The callback is invoked once the logs are discovered, after every loaded
+    /// record and after every replayed log.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O or deserialization errors during the log replay.
+    pub fn open_with_progress(
+        path: impl Into<PathBuf>,
+        callback: impl FnMut(&OpenProgress),
+    ) -> Result<KvStore> {
+        KvStore::builder().open_with_progress(path, callback)
+    }
 
-        Ok(KvStore {
-            path,
-            log,
-            uncompacted,
-            readers,
-            writer,
-            records,
-        })
+    /// Returns a `KvStoreBuilder` to configure a `KvStore` before opening it.
+    pub fn builder() -> KvStoreBuilder {
+        KvStoreBuilder::default()
     }
+}
 
+impl<S: Storage> KvStore<S> {
     /// Sets the value of a string key to a string.
     ///
     /// If the key already exists, the previous value will be overwritten.
@@ -111,6 +125,9 @@ impl KvStore {
         serde_json::to_writer(&mut self.writer, &cmd)?;
         self.writer.flush()?;
         if let MultipleCmd::Set { key, .. } = cmd {
+            if let Some(old_tombstone) = self.tombstones.remove(&key) {
+                self.uncompacted += old_tombstone.stale_len();
+            }
             if let Some(old_cmd) = self
                 .records
                 .insert(key, (self.log, pos..self.writer.pos).into())
@@ -118,6 +135,7 @@ impl KvStore {
                 self.uncompacted += old_cmd.len;
             }
         }
+        self.expire_tombstones();
         if self.uncompacted > COMPACTION_THRESHOLD {
             self.compact()?;
         }
@@ -145,6 +163,36 @@ impl KvStore {
         Ok(None)
     }
 
+    /// Serializes the value of a given key into a self-contained blob.
+    ///
+    /// The blob is versioned and checksummed, and can be passed to `restore`
+    /// to re-create the key in any store. Returns `None` if the key does not exist.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O or deserialization errors during reading the log.
+    pub fn dump(&mut self, key: String) -> Result<Option<Vec<u8>>> {
+        match self.get(key)? {
+            Some(value) => Ok(Some(dump::encode(&Dump { value })?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Re-creates a key from a blob produced by `dump`.
+    ///
+    /// If the key already exists, the previous value will be overwritten.
+    ///
+    /// # Errors
+    ///
+    /// It returns `KvsError::InvalidDump` if the blob is truncated, corrupted or
+    /// of an unsupported version.
+    ///
+    /// It propagates I/O or serialization errors during writing the log.
+    pub fn restore(&mut self, key: String, blob: &[u8]) -> Result<()> {
+        let dump = dump::decode(blob)?;
+        self.set(key, dump.value)
+    }
+
     /// Removes a given key.
     ///
     /// # Errors
@@ -154,14 +202,22 @@ impl KvStore {
     /// It propagates I/O or serialization errors during writing the log.
     pub fn remove(&mut self, key: String) -> Result<()> {
         if self.records.contains_key(&key) {
-            let cmd = MultipleCmd::rm(key);
+            let cmd = MultipleCmd::rm(key, (self.clock)().as_secs());
+            let pos = self.writer.pos;
             serde_json::to_writer(&mut self.writer, &cmd)?;
             self.writer.flush()?;
-            if let MultipleCmd::Rm { key } = cmd {
+            if let MultipleCmd::Rm { key, ts } = cmd {
                 match self.records.remove(&key) {
                     Some(old_cmd) => self.uncompacted += old_cmd.len,
                     _ => return Err(KvsError::KeyNotFound),
                 }
+                let tombstone = Tombstone {
+                    record: (self.log, pos..self.writer.pos).into(),
+                    removed_at: ts,
+                    expired: false,
+                };
+                self.expiring.push(Reverse((ts, key.clone())));
+                self.tombstones.insert(key, tombstone);
             }
             return Ok(());
         }
@@ -169,7 +225,37 @@ impl KvStore {
     }
 
     /// Clears stale entries in the log.
+    ///
+    /// Tombstones of removed keys are kept in the compacted log until their
+    /// grace period has elapsed, and purged by the first compaction after that.
+    ///
+    /// The registered `CompactionObserver`, if any, is notified of its progress.
     pub fn compact(&mut self) -> Result<()> {
+        if let Some(observer) = &self.observer {
+            observer.started();
+        }
+        match self.compact_logs() {
+            Ok(report) => {
+                if let Some(observer) = &self.observer {
+                    observer.finished(&report);
+                }
+                Ok(())
+            }
+            Err(err) => {
+                if let Some(observer) = &self.observer {
+                    observer.failed(&err);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Copies live records and retained tombstones into a new log and removes the stale logs.
+    fn compact_logs(&mut self) -> Result<CompactionReport> {
+        let started_at = (self.clock)();
+        self.expire_tombstones();
+        let stale_bytes = self.uncompacted;
+
         // increase current gen by 2. current_gen + 1 is for the compaction file.
         let compaction_log = self.log + 1;
         self.log += 2;
@@ -177,8 +263,18 @@ impl KvStore {
 
         let mut compaction_writer = self.new_log_file(compaction_log)?;
 
+        let tombstones_before = self.tombstones.len() as u64;
+        self.tombstones.retain(|_, tombstone| !tombstone.expired);
+        let tombstones_retained = self.tombstones.len() as u64;
+        let records = self.records.len() as u64;
+        let tombstones = self
+            .tombstones
+            .values_mut()
+            .map(|tombstone| &mut tombstone.record);
+
         let mut new_pos = 0; // pos in the new log file.
-        for record in &mut self.records.values_mut() {
+        let mut copied = 0;
+        for record in self.records.values_mut().chain(tombstones) {
             let reader = self.readers.get_mut(&record.log).unwrap();
             if reader.pos != record.pos {
                 reader.seek(SeekFrom::Start(record.pos))?;
@@ -188,7 +284,12 @@ impl KvStore {
             let length = io::copy(&mut cmd, &mut compaction_writer)?;
             *record = (compaction_log, new_pos..new_pos + length).into();
             new_pos += length;
+            copied += 1;
+            if let Some(observer) = &self.observer {
+                observer.progressed(new_pos, copied);
+            }
         }
+        compaction_writer.flush()?;
 
         let stale_logs: Vec<_> = self
             .readers
@@ -196,52 +297,283 @@ impl KvStore {
             .filter(|&&log| log < compaction_log)
             .cloned()
             .collect();
+        let logs_removed = stale_logs.len() as u64;
         for stale_log in stale_logs {
             self.readers.remove(&stale_log);
-            fs::remove_file(log_path(&self.path, stale_log))?;
+            self.storage.remove(stale_log)?;
         }
 
         self.uncompacted = 0;
 
-        Ok(())
+        Ok(CompactionReport {
+            records,
+            tombstones_retained,
+            tombstones_purged: tombstones_before - tombstones_retained,
+            bytes_written: new_pos,
+            stale_bytes,
+            logs_removed,
+            elapsed: (self.clock)().saturating_sub(started_at),
+        })
+    }
+
+    /// Marks the tombstones whose grace period has elapsed as expired, and
+    /// counts their remove commands as stale.
+    fn expire_tombstones(&mut self) {
+        if self.expiring.is_empty() {
+            return;
+        }
+        let now = (self.clock)().as_secs();
+        // removals are timestamped in whole seconds, so round a partial second up.
+        let grace_period = self
+            .grace_period
+            .as_secs()
+            .saturating_add(u64::from(self.grace_period.subsec_nanos() > 0));
+        while let Some(Reverse((removed_at, _))) = self.expiring.peek() {
+            if now.saturating_sub(*removed_at) < grace_period {
+                break;
+            }
+            let Reverse((removed_at, key)) = self.expiring.pop().unwrap();
+            // the entry is outdated if the key was set or removed again since.
+            if let Some(tombstone) = self.tombstones.get_mut(&key) {
+                if tombstone.removed_at == removed_at && !tombstone.expired {
+                    tombstone.expired = true;
+                    self.uncompacted += tombstone.record.len;
+                }
+            }
+        }
+    }
+
+    /// Returns an estimate of the memory used by the store.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let record_size = mem::size_of::<String>() + mem::size_of::<RecordArgs>();
+        let tombstone_size = mem::size_of::<String>() + mem::size_of::<Tombstone>();
+        let expiring_size = mem::size_of::<(u64, String)>();
+        let reader_size = mem::size_of::<u64>() + mem::size_of::<BufReaderWithPos<S::Reader>>();
+        let expiring_keys = self.expiring.iter().map(|Reverse((_, key))| key);
+        let key_bytes = self
+            .records
+            .keys()
+            .chain(self.tombstones.keys())
+            .chain(expiring_keys);
+
+        let reader_buffers = self.readers.values().map(|reader| reader.reader.capacity());
+        MemoryUsage {
+            index_entries: (self.records.len() * record_size
+                + self.tombstones.len() * tombstone_size
+                + self.expiring.len() * expiring_size) as u64,
+            key_bytes: key_bytes.map(|key| key.capacity() as u64).sum(),
+            log_buffers: (self.readers.len() * reader_size
+                + reader_buffers.sum::<usize>()
+                + self.writer.writer.capacity()) as u64,
+            storage: self.storage.memory_usage(),
+        }
     }
 
     /// Create a new log file with given generation number and add the reader to the readers map.
     ///
     /// Returns the writer to the log.
-    fn new_log_file(&mut self, gen: u64) -> Result<BufWriterWithPos<File>> {
-        new_log_file(&self.path, gen, &mut self.readers)
+    fn new_log_file(&mut self, gen: u64) -> Result<BufWriterWithPos<S::Writer>> {
+        new_log_file(&self.storage, gen, &mut self.readers)
     }
 }
 
-/// Returns sorted log files in the given directory.
-fn sorted_log_list(path: &Path) -> Result<Vec<u64>> {
-    let mut log_list: Vec<u64> = fs::read_dir(&path)?
-        .flat_map(|res| -> Result<_> { Ok(res?.path()) })
-        .filter(|path| path.is_file() && path.extension() == Some("log".as_ref()))
-        .flat_map(|path| {
-            path.file_name()
-                .and_then(OsStr::to_str)
-                .map(|s| s.trim_end_matches(".log"))
-                .map(str::parse::<u64>)
-        })
-        .flatten()
-        .collect();
-    log_list.sort_unstable();
-    Ok(log_list)
+/// An estimate of the memory used by a `KvStore`, in bytes.
+///
+/// Every entry of the index, the tombstones and the log readers is counted by
+/// its size, while keys, buffers and logs held in memory are counted by their
+/// allocated capacity. Allocator overhead and the internal nodes or buckets of
+/// the maps are not included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Memory used by the entries of the index, including tombstones.
+    pub index_entries: u64,
+    /// Memory used by the keys of the index, including tombstones.
+    pub key_bytes: u64,
+    /// Memory used by the log readers and writer, including their buffers.
+    pub log_buffers: u64,
+    /// Memory used by the storage to hold the logs, see `Storage::memory_usage`.
+    pub storage: u64,
 }
 
-fn log_path(dir: &Path, gen: u64) -> PathBuf {
-    dir.join(format!("{}.log", gen))
+impl MemoryUsage {
+    /// Returns the total memory used.
+    pub fn total(&self) -> u64 {
+        self.index_entries + self.key_bytes + self.log_buffers + self.storage
+    }
+}
+
+/// A builder for configuring and opening a `KvStore`.
+///
+/// ```rust
+/// # use kvs::{KvStore, Result};
+/// # fn try_main() -> Result<()> {
+/// use std::env::current_dir;
+/// use std::time::Duration;
+/// let mut store = KvStore::builder()
+///     .tombstone_grace_period(Duration::from_secs(60 * 60))
+///     .open(current_dir()?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct KvStoreBuilder {
+    grace_period: Duration,
+    observer: Option<Box<dyn CompactionObserver>>,
+    clock: Option<Box<dyn Fn() -> Duration + Send>>,
+}
+
+impl KvStoreBuilder {
+    /// Sets how long tombstones of removed keys are kept through compactions.
+    ///
+    /// Replication followers or incremental backups that are briefly behind
+    /// still see a removal as long as its tombstone has not been purged.
+    /// Defaults to zero, which purges tombstones on the next compaction.
+    /// It has a resolution of whole seconds, and a partial second is rounded up.
+    /// It is ignored on wasm32 without an OS unless a clock is set, see `clock`.
+    pub fn tombstone_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets the clock returning the time elapsed since the Unix epoch.
+    ///
+    /// It times tombstones and compactions. Defaults to the system clock.
+    ///
+    /// wasm32 without an OS has no system clock, so the tombstone grace period
+    /// is ignored there unless a clock is set, e.g. one reading `js_sys::Date::now`.
+    pub fn clock(mut self, clock: impl Fn() -> Duration + Send + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Registers an observer notified about the progress of every compaction.
+    pub fn compaction_observer(mut self, observer: impl CompactionObserver + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Opens a `KvStore` with the given path.
+    ///
+    /// This will create a new directory if the given one does not exist.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O or deserialization errors during the log replay.
+    pub fn open(self, path: impl Into<PathBuf>) -> Result<KvStore> {
+        self.open_with_storage(FileStorage::open(path)?)
+    }
+
+    /// Opens a `KvStore` with the given path, reporting the progress of the log replay.
+    ///
+    /// See `KvStore::open_with_progress`.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O or deserialization errors during the log replay.
+    pub fn open_with_progress(
+        self,
+        path: impl Into<PathBuf>,
+        mut callback: impl FnMut(&OpenProgress),
+    ) -> Result<KvStore> {
+        self.replay(FileStorage::open(path)?, &mut callback)
+    }
+
+    /// Opens a `KvStore` persisted in the given storage.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O or deserialization errors during the log replay.
+    pub fn open_with_storage<S: Storage>(self, storage: S) -> Result<KvStore<S>> {
+        self.replay(storage, &mut |_| {})
+    }
+
+    fn replay<S: Storage>(
+        self,
+        storage: S,
+        callback: &mut dyn FnMut(&OpenProgress),
+    ) -> Result<KvStore<S>> {
+        let mut readers = HashMap::new();
+        let mut records = BTreeMap::new();
+        let mut tombstones = HashMap::new();
+        let mut expiring = BinaryHeap::new();
+        let mut uncompacted = 0;
+
+        let log_list = storage.logs()?;
+        let mut progress = OpenProgress {
+            logs_discovered: log_list.len() as u64,
+            ..OpenProgress::default()
+        };
+        callback(&progress);
+
+        for &log in &log_list {
+            let mut reader = BufReaderWithPos::new(storage.reader(log)?)?;
+            let loaded = |bytes| {
+                progress.bytes_replayed += bytes;
+                progress.records_loaded += 1;
+                callback(&progress);
+            };
+            uncompacted += load(
+                log,
+                &mut reader,
+                &mut records,
+                &mut tombstones,
+                &mut expiring,
+                loaded,
+            )?;
+            readers.insert(log, reader);
+            progress.logs_replayed += 1;
+            callback(&progress);
+        }
+
+        let log = log_list.last().unwrap_or(&0) + 1;
+        let writer = new_log_file(&storage, log, &mut readers)?;
+
+        let mut store = KvStore {
+            storage,
+            log,
+            uncompacted,
+            readers,
+            writer,
+            records,
+            tombstones,
+            expiring,
+            grace_period: match self.clock {
+                None if !HAS_SYSTEM_CLOCK => Duration::ZERO,
+                _ => self.grace_period,
+            },
+            clock: self.clock.unwrap_or_else(|| Box::new(system_clock)),
+            observer: self.observer,
+        };
+        store.expire_tombstones();
+        Ok(store)
+    }
+}
+
+/// Progress of the log replay while opening a `KvStore`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenProgress {
+    /// Number of logs discovered in the storage.
+    pub logs_discovered: u64,
+    /// Number of logs replayed so far.
+    pub logs_replayed: u64,
+    /// Number of bytes replayed so far.
+    pub bytes_replayed: u64,
+    /// Number of records loaded so far.
+    pub records_loaded: u64,
 }
 
 /// Load the whole log file and store value locations in the index map.
 ///
+/// Calls `loaded` with the length of every loaded record.
+///
 /// Returns how many bytes can be saved after a compaction.
-fn load(
+fn load<R: Read + Seek>(
     log: u64,
-    reader: &mut BufReaderWithPos<File>,
+    reader: &mut BufReaderWithPos<R>,
     records: &mut BTreeMap<String, RecordArgs>,
+    tombstones: &mut HashMap<String, Tombstone>,
+    expiring: &mut BinaryHeap<Reverse<(u64, String)>>,
+    mut loaded: impl FnMut(u64),
 ) -> Result<u64> {
     let mut uncompacted = 0;
     // To make sure we read from the beginning of the file.
@@ -251,17 +583,29 @@ fn load(
         let new_pos = stream.byte_offset() as u64;
         match cmd? {
             MultipleCmd::Set { key, .. } => {
+                if let Some(old_tombstone) = tombstones.remove(&key) {
+                    uncompacted += old_tombstone.stale_len();
+                }
                 if let Some(old_cmd) = records.insert(key, (log, pos..new_pos).into()) {
                     uncompacted += old_cmd.len;
                 }
             }
-            MultipleCmd::Rm { key } => {
+            MultipleCmd::Rm { key, ts } => {
                 if let Some(old_cmd) = records.remove(&key) {
                     uncompacted += old_cmd.len;
                 }
-                uncompacted += new_pos - pos;
+                let tombstone = Tombstone {
+                    record: (log, pos..new_pos).into(),
+                    removed_at: ts,
+                    expired: false,
+                };
+                expiring.push(Reverse((ts, key.clone())));
+                if let Some(old_tombstone) = tombstones.insert(key, tombstone) {
+                    uncompacted += old_tombstone.stale_len();
+                }
             }
         }
+        loaded(new_pos - pos);
         pos = new_pos;
     }
     Ok(uncompacted)
@@ -270,20 +614,13 @@ fn load(
 /// Create a new log file with given generation number and add the reader to the readers map.
 ///
 /// Returns the writer to the log.
-fn new_log_file(
-    path: &Path,
+fn new_log_file<S: Storage>(
+    storage: &S,
     log: u64,
-    readers: &mut HashMap<u64, BufReaderWithPos<File>>,
-) -> Result<BufWriterWithPos<File>> {
-    let path = log_path(&path, log);
-    let writer = BufWriterWithPos::new(
-        OpenOptions::new()
-            .create(true)
-            .write(true)
-            .append(true)
-            .open(&path)?,
-    )?;
-    readers.insert(log, BufReaderWithPos::new(File::open(&path)?)?);
+    readers: &mut HashMap<u64, BufReaderWithPos<S::Reader>>,
+) -> Result<BufWriterWithPos<S::Writer>> {
+    let writer = BufWriterWithPos::new(storage.writer(log)?)?;
+    readers.insert(log, BufReaderWithPos::new(storage.reader(log)?)?);
     Ok(writer)
 }
 
@@ -304,22 +641,69 @@ impl From<(u64, Range<u64>)> for RecordArgs {
     }
 }
 
+/// Represents a remove command kept in the log after its key was removed.
+struct Tombstone {
+    record: RecordArgs,
+    // seconds since the Unix epoch at which the key was removed.
+    removed_at: u64,
+    // whether the grace period has elapsed and the bytes are counted as stale.
+    expired: bool,
+}
+
+impl Tombstone {
+    /// Returns the bytes not yet counted as stale that become stale once the
+    /// tombstone is replaced.
+    fn stale_len(&self) -> u64 {
+        if self.expired {
+            0
+        } else {
+            self.record.len
+        }
+    }
+}
+
 /// Struct representing a multiple command.
 #[derive(Deserialize, Serialize, Debug)]
 enum MultipleCmd {
-    Set { key: String, value: String },
-    Rm { key: String },
+    Set {
+        key: String,
+        value: String,
+    },
+    Rm {
+        key: String,
+        // logs written before tombstone retention have no timestamp.
+        #[serde(default)]
+        ts: u64,
+    },
 }
 
 impl MultipleCmd {
     fn set(key: String, value: String) -> MultipleCmd {
         MultipleCmd::Set { key, value }
     }
-    fn rm(key: String) -> MultipleCmd {
-        MultipleCmd::Rm { key }
+    fn rm(key: String, ts: u64) -> MultipleCmd {
+        MultipleCmd::Rm { key, ts }
     }
 }
 
+/// Returns the time elapsed since the Unix epoch.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn system_clock() -> Duration {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Returns zero, as wasm32 without an OS has no clock to read.
+///
+/// Stores opened without a clock then ignore their tombstone grace period, see
+/// `KvStoreBuilder::clock`.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn system_clock() -> Duration {
+    Duration::ZERO
+}
+
 struct BufWriterWithPos<W: Write> {
     writer: BufWriter<W>,
     pos: u64,
@@ -15,8 +15,15 @@
 #![deny(missing_docs)]
 //! A simple key/value store.
 
+pub use compaction::{CompactionObserver, CompactionReport};
 pub use error::{KvsError, Result};
-pub use kv::{KvStore, KvsEngine};
+pub use kv::{KvStore, KvStoreBuilder, KvsEngine, MemoryUsage, OpenProgress};
+pub use storage::{FileStorage, MemoryStorage, Storage};
 
+mod compaction;
+mod dump;
 mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 mod kv;
+mod storage;
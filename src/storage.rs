@@ -0,0 +1,325 @@
+// MIT License
+//
+// Copyright (c) 2023 Chunfung
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+
+use super::Result;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    fs::{File, OpenOptions},
+    io,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+/// The persistence layer of a `KvStore`.
+///
+/// A storage holds append-only logs identified by monotonically increasing
+/// generation numbers. `FileStorage` keeps them as files in a directory, and
+/// `MemoryStorage` keeps them in memory for targets without a file system,
+/// such as wasm32 in the browser.
+pub trait Storage {
+    /// Reader over a single log.
+    type Reader: Read + Seek;
+    /// Writer appending to a single log.
+    type Writer: Write + Seek;
+
+    /// Returns the generation numbers of all logs in ascending order.
+    fn logs(&self) -> Result<Vec<u64>>;
+
+    /// Opens a reader positioned at the start of the given log.
+    ///
+    /// The reader must observe bytes appended to the log after it was opened.
+    fn reader(&self, log: u64) -> Result<Self::Reader>;
+
+    /// Opens a writer appending to the given log, creating it if it does not exist.
+    fn writer(&self, log: u64) -> Result<Self::Writer>;
+
+    /// Removes the given log.
+    fn remove(&self, log: u64) -> Result<()>;
+
+    /// Returns the bytes of memory used to hold the logs.
+    ///
+    /// Defaults to zero for storages keeping the logs outside of memory.
+    fn memory_usage(&self) -> u64 {
+        0
+    }
+}
+
+/// A `Storage` keeping logs as files in a directory.
+///
+/// Log files are named after their generation numbers with a `log` extension name.
+///
+/// On Windows, a file cannot be deleted while another process holds it open
+/// without sharing deletion. Files opened by `FileStorage` explicitly share
+/// reading, writing and deletion, but other processes such as backup tools or
+/// virus scanners may not. The removal of such a log is deferred: an empty file
+/// with a `stale` extension name marks it, and it is skipped and removed again
+/// whenever the logs are listed.
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    /// Opens a `FileStorage` in the given directory.
+    ///
+    /// This will create a new directory if the given one does not exist.
+    pub fn open(path: impl Into<PathBuf>) -> Result<FileStorage> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+        Ok(FileStorage { path })
+    }
+}
+
+impl Storage for FileStorage {
+    type Reader = File;
+    type Writer = File;
+
+    fn logs(&self) -> Result<Vec<u64>> {
+        let mut log_list = Vec::new();
+        for log in sorted_log_list(&self.path)? {
+            if !self.retry_removal(log)? {
+                log_list.push(log);
+            }
+        }
+        Ok(log_list)
+    }
+
+    fn reader(&self, log: u64) -> Result<File> {
+        Ok(open_options().read(true).open(log_path(&self.path, log))?)
+    }
+
+    fn writer(&self, log: u64) -> Result<File> {
+        Ok(open_options()
+            .create(true)
+            .append(true)
+            .open(log_path(&self.path, log))?)
+    }
+
+    fn remove(&self, log: u64) -> Result<()> {
+        match fs::remove_file(log_path(&self.path, log)) {
+            Ok(()) => Ok(()),
+            Err(err) if in_use(&err) => {
+                File::create(stale_path(&self.path, log))?;
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl FileStorage {
+    /// Retries removing the given log if its removal has been deferred.
+    ///
+    /// Returns `true` if the log is stale, whether it could be removed this time or not.
+    ///
+    /// # Errors
+    ///
+    /// It propagates I/O errors other than the log still being open elsewhere.
+    fn retry_removal(&self, log: u64) -> Result<bool> {
+        let stale = stale_path(&self.path, log);
+        if !stale.is_file() {
+            return Ok(false);
+        }
+        match fs::remove_file(log_path(&self.path, log)) {
+            Ok(()) => fs::remove_file(stale)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => fs::remove_file(stale)?,
+            Err(err) if in_use(&err) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(true)
+    }
+}
+
+/// Returns the options to open log files with.
+///
+/// On Windows, the files share reading, writing and deletion with other handles,
+/// so they can be removed during compactions while still being open.
+fn open_options() -> OpenOptions {
+    #[cfg_attr(not(windows), allow(unused_mut))]
+    let mut options = OpenOptions::new();
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+
+        // FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+        options.share_mode(0x1 | 0x2 | 0x4);
+    }
+    options
+}
+
+/// Returns sorted log files in the given directory.
+fn sorted_log_list(path: &Path) -> Result<Vec<u64>> {
+    let mut log_list: Vec<u64> = fs::read_dir(path)?
+        .flat_map(|res| -> Result<_> { Ok(res?.path()) })
+        .filter(|path| path.is_file() && path.extension() == Some("log".as_ref()))
+        .flat_map(|path| {
+            path.file_name()
+                .and_then(OsStr::to_str)
+                .map(|s| s.trim_end_matches(".log"))
+                .map(str::parse::<u64>)
+        })
+        .flatten()
+        .collect();
+    log_list.sort_unstable();
+    Ok(log_list)
+}
+
+fn log_path(dir: &Path, gen: u64) -> PathBuf {
+    dir.join(format!("{}.log", gen))
+}
+
+/// Returns whether a removal failed because the file is still open elsewhere.
+fn in_use(err: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_DELETE_PENDING
+    cfg!(windows) && matches!(err.raw_os_error(), Some(32) | Some(303))
+}
+
+fn stale_path(dir: &Path, gen: u64) -> PathBuf {
+    dir.join(format!("{}.stale", gen))
+}
+
+/// A `Storage` keeping logs in memory.
+///
+/// Clones share the same logs, so a `KvStore` can be reopened from a clone
+/// of the storage it was dropped with.
+///
+/// ```rust
+/// # use kvs::{KvStore, MemoryStorage, Result};
+/// # fn try_main() -> Result<()> {
+/// let storage = MemoryStorage::new();
+/// let mut store = KvStore::builder().open_with_storage(storage.clone())?;
+/// store.set("key".to_owned(), "value".to_owned())?;
+/// drop(store);
+///
+/// let mut store = KvStore::builder().open_with_storage(storage)?;
+/// assert_eq!(store.get("key".to_owned())?, Some("value".to_owned()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    logs: Arc<Mutex<BTreeMap<u64, MemoryLog>>>,
+}
+
+type MemoryLog = Arc<RwLock<Vec<u8>>>;
+
+impl MemoryStorage {
+    /// Creates an empty `MemoryStorage`.
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    type Reader = MemoryReader;
+    type Writer = MemoryWriter;
+
+    fn logs(&self) -> Result<Vec<u64>> {
+        Ok(self.logs.lock().unwrap().keys().cloned().collect())
+    }
+
+    fn reader(&self, log: u64) -> Result<MemoryReader> {
+        match self.logs.lock().unwrap().get(&log) {
+            Some(data) => Ok(MemoryReader {
+                data: data.clone(),
+                pos: 0,
+            }),
+            None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+        }
+    }
+
+    fn writer(&self, log: u64) -> Result<MemoryWriter> {
+        let data = self.logs.lock().unwrap().entry(log).or_default().clone();
+        Ok(MemoryWriter { data })
+    }
+
+    fn remove(&self, log: u64) -> Result<()> {
+        match self.logs.lock().unwrap().remove(&log) {
+            Some(_) => Ok(()),
+            None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+        }
+    }
+
+    fn memory_usage(&self) -> u64 {
+        let logs = self.logs.lock().unwrap();
+        let data = logs.values().map(|data| data.read().unwrap().capacity());
+        data.sum::<usize>() as u64
+    }
+}
+
+/// Reader over a log of a `MemoryStorage`.
+pub struct MemoryReader {
+    data: MemoryLog,
+    pos: u64,
+}
+
+impl Read for MemoryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.data.read().unwrap();
+        let start = (self.pos as usize).min(data.len());
+        let length = buf.len().min(data.len() - start);
+        buf[..length].copy_from_slice(&data[start..start + length]);
+        self.pos += length as u64;
+        Ok(length)
+    }
+}
+
+impl Seek for MemoryReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.data.read().unwrap().len() as u64;
+        self.pos = seek_pos(self.pos, len, pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Writer appending to a log of a `MemoryStorage`.
+pub struct MemoryWriter {
+    data: MemoryLog,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.write().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryWriter {
+    // Writes always append, so the position is always the end of the log.
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let len = self.data.read().unwrap().len() as u64;
+        seek_pos(len, len, pos)?;
+        Ok(len)
+    }
+}
+
+fn seek_pos(current: u64, len: u64, pos: SeekFrom) -> io::Result<u64> {
+    let new_pos = match pos {
+        SeekFrom::Start(offset) => Some(offset),
+        SeekFrom::End(offset) => len.checked_add_signed(offset),
+        SeekFrom::Current(offset) => current.checked_add_signed(offset),
+    };
+    new_pos.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )
+    })
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "ffi")]
+
+use kvs::ffi::*;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use tempfile::TempDir;
+
+// Should set, get and remove values through the C ABI
+#[test]
+fn ffi_set_get_remove() {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let path = CString::new(temp_dir.path().to_str().unwrap()).unwrap();
+    let key = CString::new("key1").unwrap();
+    let value = CString::new("value1").unwrap();
+
+    unsafe {
+        let mut handle = ptr::null_mut();
+        assert_eq!(kvs_open(path.as_ptr(), &mut handle), KVS_OK);
+        assert_eq!(kvs_set(handle, key.as_ptr(), value.as_ptr()), KVS_OK);
+
+        let mut found = ptr::null_mut();
+        assert_eq!(kvs_get(handle, key.as_ptr(), &mut found), KVS_OK);
+        assert_eq!(CStr::from_ptr(found).to_str(), Ok("value1"));
+        kvs_free_string(found);
+
+        assert_eq!(kvs_remove(handle, key.as_ptr()), KVS_OK);
+        assert_eq!(kvs_remove(handle, key.as_ptr()), KVS_ERR_KEY_NOT_FOUND);
+        assert_eq!(
+            kvs_get(handle, key.as_ptr(), &mut found),
+            KVS_ERR_KEY_NOT_FOUND
+        );
+        assert!(found.is_null());
+        kvs_close(handle);
+    }
+}
+
+// Should reject NULL pointers instead of dereferencing them
+#[test]
+fn ffi_null_pointers() {
+    let key = CString::new("key1").unwrap();
+
+    unsafe {
+        let mut handle = ptr::null_mut();
+        assert_eq!(kvs_open(ptr::null(), &mut handle), KVS_ERR_NULL_POINTER);
+        assert!(handle.is_null());
+        assert_eq!(
+            kvs_set(handle, key.as_ptr(), key.as_ptr()),
+            KVS_ERR_NULL_POINTER
+        );
+        kvs_close(handle);
+        kvs_free_string(ptr::null_mut());
+    }
+}
@@ -1,4 +1,12 @@
-use kvs::{KvStore, KvsEngine, Result};
+use kvs::{
+    CompactionObserver, CompactionReport, KvStore, KvsEngine, KvsError, MemoryStorage, Result,
+    Storage,
+};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempfile::TempDir;
 use walkdir::WalkDir;
 
@@ -79,6 +87,315 @@ fn remove_key() -> Result<()> {
     Ok(())
 }
 
+// Should keep tombstones in the compacted log during the grace period
+#[test]
+fn retain_tombstone_within_grace_period() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::builder()
+        .tombstone_grace_period(Duration::from_secs(60 * 60))
+        .open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.remove("key1".to_owned())?;
+    store.compact()?;
+    assert!(log_content(temp_dir.path()).contains("key1"));
+
+    // Open from disk again and check the key stays removed
+    drop(store);
+    let mut store = KvStore::open(temp_dir.path())?;
+    assert_eq!(store.get("key1".to_owned())?, None);
+
+    Ok(())
+}
+
+// Should purge tombstones on compaction once the grace period has elapsed
+#[test]
+fn purge_tombstone_after_grace_period() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.remove("key1".to_owned())?;
+    store.compact()?;
+    assert!(!log_content(temp_dir.path()).contains("key1"));
+
+    Ok(())
+}
+
+// Should purge tombstones on the first compaction after a non-zero grace period
+#[test]
+fn purge_tombstone_after_elapsed_grace_period() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let now = Arc::new(AtomicU64::new(1000));
+    let stale_bytes = Arc::new(AtomicU64::new(0));
+    let clock_now = now.clone();
+    let mut store = KvStore::builder()
+        .tombstone_grace_period(Duration::from_secs(60))
+        .clock(move || Duration::from_secs(clock_now.load(Ordering::SeqCst)))
+        .compaction_observer(StaleBytes(stale_bytes.clone()))
+        .open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.remove("key1".to_owned())?;
+
+    now.store(1030, Ordering::SeqCst);
+    store.compact()?;
+    assert!(log_content(temp_dir.path()).contains("key1"));
+
+    // The expired tombstone counts as stale and is purged
+    now.store(1060, Ordering::SeqCst);
+    store.compact()?;
+    assert!(!log_content(temp_dir.path()).contains("key1"));
+    let tombstone = r#"{"Rm":{"key":"key1","ts":1000}}"#;
+    assert_eq!(stale_bytes.load(Ordering::SeqCst), tombstone.len() as u64);
+
+    Ok(())
+}
+
+// Should keep tombstones for a whole second under a sub-second grace period
+#[test]
+fn round_up_sub_second_grace_period() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let now = Arc::new(AtomicU64::new(1000));
+    let clock_now = now.clone();
+    let mut store = KvStore::builder()
+        .tombstone_grace_period(Duration::from_millis(500))
+        .clock(move || Duration::from_millis(clock_now.load(Ordering::SeqCst)))
+        .open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.remove("key1".to_owned())?;
+
+    now.store(1600, Ordering::SeqCst);
+    store.compact()?;
+    assert!(log_content(temp_dir.path()).contains("key1"));
+
+    now.store(2000, Ordering::SeqCst);
+    store.compact()?;
+    assert!(!log_content(temp_dir.path()).contains("key1"));
+
+    Ok(())
+}
+
+// Should count every stale command once, whether written or replayed
+#[test]
+fn count_stale_bytes_once() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let stale_bytes = Arc::new(AtomicU64::new(0));
+    let mut store = KvStore::open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.remove("key1".to_owned())?;
+    store.set("key1".to_owned(), "value2".to_owned())?;
+    let live = r#"{"Set":{"key":"key1","value":"value2"}}"#;
+    let expected = (log_content(temp_dir.path()).len() - live.len()) as u64;
+
+    drop(store);
+    let mut store = KvStore::builder()
+        .compaction_observer(StaleBytes(stale_bytes.clone()))
+        .open(temp_dir.path())?;
+    store.compact()?;
+    assert_eq!(stale_bytes.load(Ordering::SeqCst), expected);
+
+    store.remove("key1".to_owned())?;
+    store.set("key1".to_owned(), "value2".to_owned())?;
+    let expected = (log_content(temp_dir.path()).len() - live.len()) as u64;
+    store.compact()?;
+    assert_eq!(stale_bytes.load(Ordering::SeqCst), expected);
+
+    Ok(())
+}
+
+// Records the stale bytes of the last compaction.
+struct StaleBytes(Arc<AtomicU64>);
+
+impl CompactionObserver for StaleBytes {
+    fn finished(&self, report: &CompactionReport) {
+        self.0.store(report.stale_bytes, Ordering::SeqCst);
+    }
+}
+
+// Should notify the compaction observer about every stage of a compaction
+#[test]
+fn observe_compaction() -> Result<()> {
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl CompactionObserver for Recorder {
+        fn started(&self) {
+            self.0.lock().unwrap().push("started".to_owned());
+        }
+        fn progressed(&self, _bytes: u64, records: u64) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("progressed {}", records));
+        }
+        fn finished(&self, report: &CompactionReport) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("finished {}", report.records));
+        }
+    }
+
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let recorder = Recorder::default();
+    let mut store = KvStore::builder()
+        .compaction_observer(recorder.clone())
+        .open(temp_dir.path())?;
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.set("key2".to_owned(), "value2".to_owned())?;
+    store.set("key1".to_owned(), "value3".to_owned())?;
+    store.compact()?;
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec!["started", "progressed 1", "progressed 2", "finished 2"]
+    );
+    assert_eq!(store.get("key1".to_owned())?, Some("value3".to_owned()));
+
+    Ok(())
+}
+
+// Should account keys and log buffers in the memory usage
+#[test]
+fn memory_usage() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+
+    let empty = store.memory_usage();
+    assert_eq!(empty.key_bytes, 0);
+    assert!(empty.log_buffers > 0);
+    assert_eq!(empty.storage, 0);
+
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.set("key2".to_owned(), "value2".to_owned())?;
+    let usage = store.memory_usage();
+    assert_eq!(usage.key_bytes, 8);
+    assert!(usage.index_entries > empty.index_entries);
+    assert_eq!(
+        usage.total(),
+        usage.index_entries + usage.key_bytes + usage.log_buffers
+    );
+
+    let mut store = KvStore::builder().open_with_storage(MemoryStorage::new())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    assert!(store.memory_usage().storage > 0);
+
+    Ok(())
+}
+
+// Should report every log and record replayed while opening
+#[test]
+fn open_with_progress() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.set("key2".to_owned(), "value2".to_owned())?;
+    drop(store);
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.remove("key1".to_owned())?;
+    drop(store);
+
+    let mut reports = Vec::new();
+    let mut store = KvStore::open_with_progress(temp_dir.path(), |progress| {
+        reports.push(*progress);
+    })?;
+    let last = reports.last().expect("no progress reported");
+    assert_eq!(reports[0].logs_discovered, 2);
+    assert_eq!(reports[0].records_loaded, 0);
+    assert_eq!(last.logs_replayed, 2);
+    assert_eq!(last.records_loaded, 3);
+    assert_eq!(
+        last.bytes_replayed,
+        log_content(temp_dir.path()).len() as u64
+    );
+    assert_eq!(reports.len(), 1 + 3 + 2);
+    assert_eq!(store.get("key2".to_owned())?, Some("value2".to_owned()));
+
+    Ok(())
+}
+
+// Should re-create a dumped key in another store
+#[test]
+fn dump_and_restore() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+
+    let blob = store.dump("key1".to_owned())?.expect("key1 not dumped");
+    assert_eq!(store.dump("key2".to_owned())?, None);
+
+    let mut other = KvStore::builder().open_with_storage(MemoryStorage::new())?;
+    other.restore("key2".to_owned(), &blob)?;
+    assert_eq!(other.get("key2".to_owned())?, Some("value1".to_owned()));
+
+    Ok(())
+}
+
+// Should reject truncated or corrupted dumps
+#[test]
+fn restore_invalid_dump() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    let blob = store.dump("key1".to_owned())?.expect("key1 not dumped");
+
+    let mut corrupted = blob.clone();
+    corrupted[3] ^= 1;
+    // A newer version with a valid checksum
+    let mut unsupported = blob[..blob.len() - 4].to_vec();
+    unsupported[0] += 1;
+    let checksum = crc32(&unsupported);
+    unsupported.extend_from_slice(&checksum.to_le_bytes());
+    for invalid in [
+        &blob[..blob.len() - 1],
+        &corrupted[..],
+        &[],
+        &unsupported[..],
+    ] {
+        match store.restore("key2".to_owned(), invalid) {
+            Err(KvsError::InvalidDump) => {}
+            res => panic!("unexpected result: {:?}", res),
+        }
+    }
+    assert_eq!(store.get("key2".to_owned())?, None);
+
+    // The same blob is restored with the current version, so only the version is rejected
+    unsupported.truncate(unsupported.len() - 4);
+    unsupported[0] -= 1;
+    let checksum = crc32(&unsupported);
+    unsupported.extend_from_slice(&checksum.to_le_bytes());
+    store.restore("key2".to_owned(), &unsupported)?;
+    assert_eq!(store.get("key2".to_owned())?, Some("value1".to_owned()));
+
+    Ok(())
+}
+
+// The CRC-32 (IEEE 802.3) checksum closing a dump blob.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn log_content(path: &Path) -> String {
+    WalkDir::new(path)
+        .into_iter()
+        .map(|res| res.expect("fail to walk directory"))
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| fs::read_to_string(entry.path()).expect("fail to read log file"))
+        .collect()
+}
+
 // Insert data until total size of the directory decreases.
 // Test data correctness after compaction.
 #[test]
@@ -124,3 +441,34 @@ fn compaction() -> Result<()> {
 
     panic!("No compaction detected");
 }
+
+// Should persist and compact data in a memory storage the same way as on disk
+#[test]
+fn memory_storage() -> Result<()> {
+    let storage = MemoryStorage::new();
+    let mut store = KvStore::builder().open_with_storage(storage.clone())?;
+
+    for iter in 0..1000 {
+        for key_id in 0..1000 {
+            let key = format!("key{}", key_id);
+            let value = format!("{}", iter);
+            store.set(key, value)?;
+        }
+
+        // The first log is removed once compaction is triggered
+        if storage.logs()?[0] == 1 {
+            continue;
+        }
+
+        drop(store);
+        // reopen and check content
+        let mut store = KvStore::builder().open_with_storage(storage.clone())?;
+        for key_id in 0..1000 {
+            let key = format!("key{}", key_id);
+            assert_eq!(store.get(key)?, Some(format!("{}", iter)));
+        }
+        return Ok(());
+    }
+
+    panic!("No compaction detected");
+}
@@ -0,0 +1,63 @@
+#![cfg(windows)]
+
+use kvs::{KvStore, Result};
+use std::fs::OpenOptions;
+use std::os::windows::fs::OpenOptionsExt;
+use tempfile::TempDir;
+
+// Sharing mode allowing other handles to read but not to delete the file.
+const FILE_SHARE_READ: u32 = 0x1;
+
+// Should compact while another process holds a stale log open without sharing deletion
+#[test]
+fn compaction_with_locked_log() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.set("key2".to_owned(), "value2".to_owned())?;
+    // Move both values to 2.log, so that the writer rolls over to 3.log
+    store.compact()?;
+    store.remove("key1".to_owned())?;
+
+    let locked = OpenOptions::new()
+        .read(true)
+        .share_mode(FILE_SHARE_READ)
+        .open(temp_dir.path().join("2.log"))?;
+    store.compact()?;
+    assert!(temp_dir.path().join("2.stale").exists());
+    assert!(!temp_dir.path().join("3.log").exists());
+
+    // Open from disk again and check the stale log is not replayed
+    drop(store);
+    let mut store = KvStore::open(temp_dir.path())?;
+    assert_eq!(store.get("key1".to_owned())?, None);
+    assert_eq!(store.get("key2".to_owned())?, Some("value2".to_owned()));
+
+    // The removal completes once the log is no longer locked
+    drop(locked);
+    drop(store);
+    let mut store = KvStore::open(temp_dir.path())?;
+    assert!(!temp_dir.path().join("2.log").exists());
+    assert!(!temp_dir.path().join("2.stale").exists());
+    assert_eq!(store.get("key1".to_owned())?, None);
+    assert_eq!(store.get("key2".to_owned())?, Some("value2".to_owned()));
+
+    Ok(())
+}
+
+// Should remove stale logs of compactions while their readers are open in this process
+#[test]
+fn compaction_removes_logs() -> Result<()> {
+    let temp_dir = TempDir::new().expect("unable to create temporary working directory");
+    let mut store = KvStore::open(temp_dir.path())?;
+    store.set("key1".to_owned(), "value1".to_owned())?;
+    store.compact()?;
+    store.set("key1".to_owned(), "value2".to_owned())?;
+    store.compact()?;
+
+    assert!(!temp_dir.path().join("1.log").exists());
+    assert!(!temp_dir.path().join("3.log").exists());
+    assert_eq!(store.get("key1".to_owned())?, Some("value2".to_owned()));
+
+    Ok(())
+}